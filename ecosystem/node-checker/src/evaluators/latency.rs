@@ -0,0 +1,167 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{direct::DirectEvaluatorInput, Evaluator};
+use crate::{configuration::NodeAddress, evaluator::EvaluationResult};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use clap::Parser;
+use poem_openapi::Object as PoemObject;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+const CATEGORY: &str = "latency";
+
+/// Default RTT used to seed a cold Peak-EWMA estimate, so a target's
+/// estimate starts from this value rather than from its first sample; a
+/// fast first sample only pulls it down gradually.
+const DEFAULT_SEED_RTT: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, Deserialize, Parser, PoemObject, Serialize)]
+pub struct LatencyEvaluatorArgs {
+    /// The decay time constant (tau), in seconds, used by the Peak-EWMA
+    /// estimator. Smaller values make the estimate track recent samples
+    /// more closely; larger values make it forget a latency spike more
+    /// slowly.
+    #[clap(long, default_value = "30")]
+    pub tau_secs: f64,
+
+    /// If the Peak-EWMA latency estimate, in milliseconds, exceeds this
+    /// value, the evaluation is considered failing.
+    #[clap(long, default_value = "1000")]
+    pub alert_latency_threshold_ms: u64,
+}
+
+/// A Peak-EWMA estimator of round-trip latency, in the style of the one
+/// used by load balancers such as Finagle's P2C. Every observed sample
+/// updates the estimate: if the sample exceeds the current estimate, the
+/// estimate jumps straight to the sample (the "peak"); otherwise it decays
+/// exponentially toward the sample based on how long it has been since the
+/// last update. This means a single slow response immediately drags the
+/// estimate up, while a run of fast responses only slowly pulls it back
+/// down, so the estimate reflects worst-case recent behavior rather than
+/// whichever sample happened to be measured last.
+#[derive(Debug)]
+struct PeakEwma {
+    tau: Duration,
+    estimate: Duration,
+    last_update: Instant,
+}
+
+impl PeakEwma {
+    fn new(tau: Duration, seed: Duration) -> Self {
+        Self {
+            tau,
+            estimate: seed,
+            last_update: Instant::now(),
+        }
+    }
+
+    /// Fold a newly observed RTT into the estimate.
+    fn observe(&mut self, sample: Duration) {
+        let now = Instant::now();
+        if sample >= self.estimate {
+            self.estimate = sample;
+        } else {
+            let elapsed = now.saturating_duration_since(self.last_update);
+            let w = (-elapsed.as_secs_f64() / self.tau.as_secs_f64()).exp();
+            let estimate_secs = self.estimate.as_secs_f64() * w + sample.as_secs_f64() * (1.0 - w);
+            self.estimate = Duration::from_secs_f64(estimate_secs.max(0.0));
+        }
+        self.last_update = now;
+    }
+}
+
+/// Evaluates the responsiveness of the target node. Previously this scored
+/// a single round-trip measurement taken during `evaluate`. A lone sample
+/// is noisy and, worse, a single `run()` call only ever yields one of them,
+/// so this now maintains a Peak-EWMA estimate per target `NodeAddress`
+/// across calls: each `evaluate` folds in a fresh sample for that target
+/// and scores against its decayed peak rather than the raw sample. Keying
+/// by target keeps a fast node's history from being polluted by a slow
+/// one's when the same evaluator instance is used to check several
+/// targets. The estimate is only updated when `evaluate` is called, i.e.
+/// once per `run`; the background scrape loops of `ContinuousRunner`
+/// collect metrics only and do not feed samples into this estimator.
+#[derive(Clone, Debug)]
+pub struct LatencyEvaluator {
+    args: LatencyEvaluatorArgs,
+    estimators: Arc<Mutex<HashMap<NodeAddress, PeakEwma>>>,
+}
+
+impl LatencyEvaluator {
+    pub fn new(args: LatencyEvaluatorArgs) -> Self {
+        Self {
+            args,
+            estimators: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Measure a single round trip to the target node.
+    async fn measure_rtt(target_node_address: &NodeAddress) -> Result<Duration> {
+        let start = Instant::now();
+        reqwest::get(target_node_address.url.clone())
+            .await
+            .context("Failed to reach target node to measure latency")?;
+        Ok(start.elapsed())
+    }
+}
+
+#[async_trait]
+impl Evaluator for LatencyEvaluator {
+    type Input = DirectEvaluatorInput;
+    type Error = anyhow::Error;
+
+    /// Scores the target node's responsiveness using the Peak-EWMA estimate
+    /// of its round-trip latency, updated with a fresh sample taken now,
+    /// rather than scoring the fresh sample directly.
+    async fn evaluate(&self, input: &Self::Input) -> Result<Vec<EvaluationResult>> {
+        let sample = Self::measure_rtt(&input.target_node_address).await?;
+
+        let estimate = {
+            let tau = Duration::from_secs_f64(self.args.tau_secs);
+            let mut estimators = self.estimators.lock().unwrap();
+            let estimator = estimators
+                .entry(input.target_node_address.clone())
+                .or_insert_with(|| PeakEwma::new(tau, DEFAULT_SEED_RTT));
+            estimator.observe(sample);
+            estimator.estimate
+        };
+
+        let threshold = Duration::from_millis(self.args.alert_latency_threshold_ms);
+        let score = if estimate <= threshold {
+            100
+        } else {
+            let ratio = threshold.as_secs_f64() / estimate.as_secs_f64();
+            (ratio * 100.0).clamp(0.0, 100.0) as u8
+        };
+
+        let headline = if score == 100 {
+            "Latency within expected bounds"
+        } else {
+            "Latency exceeded the alert threshold"
+        };
+
+        let evaluation_results = vec![EvaluationResult {
+            headline: headline.to_string(),
+            score,
+            explanation: format!(
+                "The Peak-EWMA estimate of the target node's round trip latency is \
+                 {:.2}ms (most recent sample: {:.2}ms), against an alert threshold of {}ms.",
+                estimate.as_secs_f64() * 1000.0,
+                sample.as_secs_f64() * 1000.0,
+                self.args.alert_latency_threshold_ms,
+            ),
+            category: CATEGORY.to_string(),
+            evaluator_name: "latency".to_string(),
+            links: vec![],
+        }];
+
+        Ok(evaluation_results)
+    }
+}
@@ -0,0 +1,268 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{direct::DirectEvaluatorInput, Evaluator};
+use crate::evaluator::EvaluationResult;
+use anyhow::{Context, Result};
+use aptos_rest_client::{Client as AptosRestClient, FaucetClient};
+use aptos_sdk::{
+    transaction_builder::TransactionFactory,
+    types::{account_address::AccountAddress, LocalAccount},
+};
+use async_trait::async_trait;
+use clap::Parser;
+use log::warn;
+use poem_openapi::Object as PoemObject;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// How much to fund each throwaway account used by the sustained load test
+/// with, in octas. This only needs to cover the gas for the transfers the
+/// account will submit over the course of the test.
+const LOAD_TEST_FUNDING_AMOUNT: u64 = 100_000_000;
+
+const CATEGORY: &str = "tps";
+
+#[derive(Clone, Debug, Deserialize, Parser, PoemObject, Serialize)]
+pub struct TpsEvaluatorArgs {
+    /// The minimum TPS we expect the target node to be able to sustain for
+    /// the evaluation to be considered a pass.
+    #[clap(long, default_value = "100")]
+    pub minimum_tps: u64,
+
+    /// How many concurrent workers submit transactions against the target
+    /// node while the sustained load test is running.
+    #[clap(long, default_value = "10")]
+    pub threads: usize,
+
+    /// How long, in seconds, to drive sustained load against the target
+    /// node for. Per the TODO on `BlockingRunner`, this evaluator can run
+    /// longer than `metrics_fetch_delay_secs`; the runner accounts for that.
+    #[clap(long, default_value = "30")]
+    pub duration_secs: u64,
+}
+
+/// The outcome of a single worker's share of the sustained load test.
+#[derive(Debug, Default)]
+struct WorkerOutcome {
+    passed: u64,
+    failed: u64,
+    elapsed: Duration,
+}
+
+/// Owns the worker task handles for a sustained load test. If this is
+/// dropped before `join_all` finishes draining it, e.g. because the runner
+/// aborted us for exceeding its overall deadline, the still-outstanding
+/// worker tasks are aborted rather than left to keep submitting
+/// transactions in the background after the evaluation has moved on.
+struct WorkerHandles(Vec<tokio::task::JoinHandle<WorkerOutcome>>);
+
+impl Drop for WorkerHandles {
+    fn drop(&mut self) {
+        for handle in &self.0 {
+            handle.abort();
+        }
+    }
+}
+
+impl WorkerHandles {
+    async fn join_all(mut self) -> Result<Vec<WorkerOutcome>> {
+        let mut outcomes = Vec::with_capacity(self.0.len());
+        // Await the handles in place rather than moving them out, so that a
+        // handle still being awaited when we are dropped is aborted too.
+        for handle in self.0.iter_mut() {
+            outcomes.push(handle.await.context("TPS evaluator worker task panicked")?);
+        }
+        Ok(outcomes)
+    }
+}
+
+#[derive(Debug)]
+pub struct TpsEvaluator {
+    args: TpsEvaluatorArgs,
+}
+
+impl TpsEvaluator {
+    pub fn new(args: TpsEvaluatorArgs) -> Self {
+        Self { args }
+    }
+
+    /// Submit a single transfer transaction against the target node and
+    /// confirm that it landed. Failures here are returned to the caller,
+    /// which is responsible for counting them as part of the pass/fail
+    /// ratio rather than failing the whole evaluator.
+    async fn submit_transaction(
+        client: &AptosRestClient,
+        account: &mut LocalAccount,
+        transaction_factory: &TransactionFactory,
+        recipient: AccountAddress,
+    ) -> Result<()> {
+        let transaction =
+            account.sign_with_transaction_builder(transaction_factory.transfer(recipient, 1));
+        client
+            .submit_and_wait(&transaction)
+            .await
+            .context("Failed to submit and confirm transaction")?;
+        Ok(())
+    }
+
+    /// Drive sustained load against the target node for `duration_secs`
+    /// using `threads` concurrent workers, each submitting transactions in
+    /// a tight loop for the duration and tracking how many passed vs
+    /// failed. Returns the aggregate throughput across all workers combined
+    /// (in transactions per second, comparable directly against
+    /// `minimum_tps`) and the overall success ratio.
+    async fn run_load_test(
+        &self,
+        client: AptosRestClient,
+        mut accounts: Vec<LocalAccount>,
+        recipient: AccountAddress,
+    ) -> Result<(f64, f64)> {
+        let transaction_factory = TransactionFactory::new(client.chain_id().await?);
+        let duration = Duration::from_secs(self.args.duration_secs);
+
+        let mut worker_handles = WorkerHandles(Vec::with_capacity(self.args.threads));
+        for mut account in accounts.drain(..) {
+            let client = client.clone();
+            let transaction_factory = transaction_factory.clone();
+            worker_handles.0.push(tokio::spawn(async move {
+                let mut outcome = WorkerOutcome::default();
+                let start = Instant::now();
+                while start.elapsed() <= duration {
+                    match Self::submit_transaction(
+                        &client,
+                        &mut account,
+                        &transaction_factory,
+                        recipient,
+                    )
+                    .await
+                    {
+                        Ok(()) => outcome.passed += 1,
+                        Err(error) => {
+                            warn!(
+                                "TPS evaluator worker failed to submit transaction: {:#}",
+                                error
+                            );
+                            outcome.failed += 1;
+                        }
+                    }
+                }
+                outcome.elapsed = start.elapsed();
+                outcome
+            }));
+        }
+
+        let mut total_passed = 0;
+        let mut total_failed = 0;
+        let mut total_elapsed = Duration::ZERO;
+        for outcome in worker_handles.join_all().await? {
+            total_passed += outcome.passed;
+            total_failed += outcome.failed;
+            total_elapsed += outcome.elapsed;
+        }
+
+        let num_workers = self.args.threads.max(1) as f64;
+        // Workers run concurrently for (approximately) the same wall-clock
+        // duration, so averaging their elapsed time gives us that duration
+        // without assuming it exactly matches `duration_secs` (a worker can
+        // run slightly over while finishing an in-flight submission).
+        let average_elapsed_secs = (total_elapsed.as_secs_f64() / num_workers).max(f64::EPSILON);
+        let aggregate_tps = total_passed as f64 / average_elapsed_secs;
+        let total_submitted = total_passed + total_failed;
+        let success_ratio = if total_submitted == 0 {
+            0.0
+        } else {
+            total_passed as f64 / total_submitted as f64
+        };
+
+        Ok((aggregate_tps, success_ratio))
+    }
+}
+
+#[async_trait]
+impl Evaluator for TpsEvaluator {
+    type Input = DirectEvaluatorInput;
+    type Error = anyhow::Error;
+
+    /// Assesses whether the target node can sustain load at or above
+    /// `minimum_tps` for `duration_secs`, using `threads` concurrent
+    /// workers to submit transactions for the full duration and reporting
+    /// both the achieved aggregate throughput and the fraction of
+    /// submissions that succeeded.
+    async fn evaluate(&self, input: &Self::Input) -> Result<Vec<EvaluationResult>> {
+        let client = AptosRestClient::new(input.target_node_address.url.clone());
+        let faucet_client = FaucetClient::new(
+            input.target_node_address.url.clone(),
+            input.target_node_address.url.clone(),
+        );
+
+        let mut rng = rand::rngs::OsRng;
+        let recipient = LocalAccount::generate(&mut rng).address();
+
+        let mut accounts = Vec::with_capacity(self.args.threads);
+        for _ in 0..self.args.threads {
+            let account = LocalAccount::generate(&mut rng);
+            faucet_client
+                .fund(account.address(), LOAD_TEST_FUNDING_AMOUNT)
+                .await
+                .context("Failed to fund account for TPS load test")?;
+            accounts.push(account);
+        }
+
+        let (aggregate_tps, success_ratio) = self
+            .run_load_test(client, accounts, recipient)
+            .await
+            .context("Failed to run TPS load test")?;
+
+        let tps_score = if aggregate_tps >= self.args.minimum_tps as f64 {
+            100
+        } else {
+            ((aggregate_tps / self.args.minimum_tps as f64) * 100.0) as u8
+        };
+        let tps_headline = if tps_score == 100 {
+            "Target achieved sufficient TPS"
+        } else {
+            "Target did not achieve sufficient TPS"
+        };
+
+        let success_ratio_score = (success_ratio * 100.0) as u8;
+        let success_ratio_headline = if success_ratio_score == 100 {
+            "Target successfully processed submitted transactions"
+        } else {
+            "Target failed to process some submitted transactions"
+        };
+
+        let evaluation_results = vec![
+            EvaluationResult {
+                headline: tps_headline.to_string(),
+                score: tps_score,
+                explanation: format!(
+                    "The target node achieved an aggregate {:.2} TPS over a {} second \
+                     sustained load test with {} workers, against a minimum of {} TPS.",
+                    aggregate_tps,
+                    self.args.duration_secs,
+                    self.args.threads,
+                    self.args.minimum_tps
+                ),
+                category: CATEGORY.to_string(),
+                evaluator_name: "sustained_tps".to_string(),
+                links: vec![],
+            },
+            EvaluationResult {
+                headline: success_ratio_headline.to_string(),
+                score: success_ratio_score,
+                explanation: format!(
+                    "{:.2}% of the transactions submitted during the sustained load test \
+                     were accepted and confirmed by the target node.",
+                    success_ratio * 100.0
+                ),
+                category: CATEGORY.to_string(),
+                evaluator_name: "sustained_tps".to_string(),
+                links: vec![],
+            },
+        ];
+
+        Ok(evaluation_results)
+    }
+}
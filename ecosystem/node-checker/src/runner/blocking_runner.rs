@@ -1,10 +1,10 @@
 // Copyright (c) Aptos
 // SPDX-License-Identifier: Apache-2.0
 
-use super::{Runner, RunnerError};
+use super::{Runner, RunnerError, RunnerPhase};
 use crate::{
     configuration::NodeAddress,
-    evaluator::{EvaluationSummary, Evaluator},
+    evaluator::{EvaluationResult, EvaluationSummary, Evaluator},
     evaluators::{
         direct::{DirectEvaluatorInput, NodeIdentityEvaluator},
         metrics::{parse_metrics, MetricsEvaluatorInput},
@@ -17,16 +17,37 @@ use crate::{
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use clap::Parser;
-use log::{debug, info};
+use futures::future::try_join_all;
+use log::{debug, info, warn};
 use poem_openapi::Object as PoemObject;
 use prometheus_parse::Scrape as PrometheusScrape;
 use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
 use tokio::time::{Duration, Instant};
 
 #[derive(Clone, Debug, Deserialize, Parser, PoemObject, Serialize)]
 pub struct BlockingRunnerArgs {
     #[clap(long, default_value = "5")]
     pub metrics_fetch_delay_secs: u64,
+
+    /// If set, the overall deadline for a single `run`, in seconds. If this
+    /// elapses, outstanding metric collection and evaluator futures are
+    /// dropped (and, in the case of the TPS evaluator's worker pool,
+    /// aborted) and `run` returns `RunnerError::Timeout`, which carries
+    /// whatever evaluation results were already produced. If unset, `run`
+    /// has no overall deadline, only the implicit ones of its individual
+    /// steps (e.g. HTTP client timeouts).
+    #[clap(long)]
+    pub run_deadline_secs: Option<u64>,
+}
+
+/// State shared between `run` and the task it races against the deadline,
+/// so that if the deadline wins, the caller can still see what phase we
+/// were in and what evaluation results we had already produced.
+#[derive(Debug, Default)]
+struct RunProgress {
+    phase: Mutex<RunnerPhase>,
+    evaluation_results: Mutex<Vec<EvaluationResult>>,
 }
 
 #[derive(Debug)]
@@ -69,22 +90,16 @@ impl<M: MetricCollector> BlockingRunner<M> {
             .await
             .map_err(RunnerError::MetricCollectorError)
     }
-}
 
-/// This runner doesn't block in the multithreading sense, but from the user
-/// perspective. To run the health check, we pull metrics once, wait, and then
-/// pull the metrics again. It does not support continually running beyond this
-/// point. You can imagine smarter versions of this where you store the last seen
-/// set of metrics, then compare against that, or perhaps even multiple previously
-/// seen sets of metrics and do more complex analysis. Additionally we could leverage
-/// things like long polling +/ sticky routing to make it that the client request
-/// doesn't just hang waiting for the run to complete.
-#[async_trait]
-impl<M: MetricCollector> Runner for BlockingRunner<M> {
-    async fn run<T: MetricCollector>(
+    /// Do the actual work of `run`, recording our phase and any evaluation
+    /// results we produce into `progress` as we go. If we get cancelled
+    /// (because the caller raced us against a deadline and the deadline won)
+    /// `progress` is how the caller recovers a partial result.
+    async fn run_inner<T: MetricCollector>(
         &self,
         target_node_address: &NodeAddress,
         target_metric_collector: &T,
+        progress: &RunProgress,
     ) -> Result<EvaluationSummary, RunnerError> {
         info!("Running evaluation for {}", target_node_address.url);
 
@@ -93,12 +108,18 @@ impl<M: MetricCollector> Runner for BlockingRunner<M> {
             target_node_address: target_node_address.clone(),
         };
 
+        *progress.phase.lock().unwrap() = RunnerPhase::NodeIdentityCheck;
         debug!("Confirming node identity matches");
         let node_identity_evaluations = self
             .node_identity_evaluator
             .evaluate(&direct_evaluator_input)
             .await
             .map_err(RunnerError::NodeIdentityEvaluatorError)?;
+        progress
+            .evaluation_results
+            .lock()
+            .unwrap()
+            .extend(node_identity_evaluations.iter().cloned());
 
         // Exit early if a node identity evaluation returned a non-passing result.
         for evaluation in &node_identity_evaluations {
@@ -107,31 +128,33 @@ impl<M: MetricCollector> Runner for BlockingRunner<M> {
             }
         }
 
-        debug!("Collecting system information from baseline node");
-        let baseline_system_information = self
-            .baseline_metric_collector
-            .collect_system_information()
-            .await
-            .map_err(RunnerError::MetricCollectorError)?;
-        debug!("{:?}", baseline_system_information);
+        *progress.phase.lock().unwrap() = RunnerPhase::FirstScrape;
+        debug!("Collecting system information and first round of metrics from both nodes");
+        let (
+            baseline_system_information,
+            target_system_information,
+            first_baseline_metrics,
+            first_target_metrics,
+        ) = tokio::try_join!(
+            async {
+                self.baseline_metric_collector
+                    .collect_system_information()
+                    .await
+                    .map_err(RunnerError::MetricCollectorError)
+            },
+            async {
+                target_metric_collector
+                    .collect_system_information()
+                    .await
+                    .map_err(RunnerError::MetricCollectorError)
+            },
+            Self::collect_metrics(&self.baseline_metric_collector),
+            Self::collect_metrics(target_metric_collector),
+        )?;
 
-        debug!("Collecting system information from target node");
-        let target_system_information = target_metric_collector
-            .collect_system_information()
-            .await
-            .map_err(RunnerError::MetricCollectorError)?;
+        debug!("{:?}", baseline_system_information);
         debug!("{:?}", target_system_information);
 
-        debug!("Collecting first round of baseline metrics");
-        let first_baseline_metrics = self
-            .baseline_metric_collector
-            .collect_metrics()
-            .await
-            .map_err(RunnerError::MetricCollectorError)?;
-
-        debug!("Collecting first round of target metrics");
-        let first_target_metrics = Self::collect_metrics(target_metric_collector).await?;
-
         let first_baseline_metrics = self.parse_response(first_baseline_metrics)?;
         let first_target_metrics = self.parse_response(first_target_metrics)?;
 
@@ -143,10 +166,10 @@ impl<M: MetricCollector> Runner for BlockingRunner<M> {
         // metrics_fetch_delay. TODO: Change it to metrics_fetch_delay_minimum
         // and make each evaluator handle the fact that the delay could be longer.
         // If the specific amount of time matters to a future evaluator, pass it
-        // in to that evaluator and it can slice up the delta as necessary.
-
-        // TODO: We could also get some slight speed wins if we awaited this
-        // evaluator and all the metric collection futures together.
+        // in to that evaluator and it can slice up the delta as necessary. Now
+        // that `run` can be bounded by `run_deadline_secs`, a misconfigured TPS
+        // evaluator no longer risks hanging the whole health check forever,
+        // just until the deadline.
 
         let metrics_fetch_delay_time =
             Instant::now() + Duration::from_secs(self.args.metrics_fetch_delay_secs);
@@ -157,24 +180,30 @@ impl<M: MetricCollector> Runner for BlockingRunner<M> {
         });
 
         if let Some(tps_evaluator) = tps_evaluator {
+            *progress.phase.lock().unwrap() = RunnerPhase::Tps;
             debug!("Starting TPS evaluator");
-            evaluation_results.append(
-                &mut tps_evaluator
-                    .evaluate(&direct_evaluator_input)
-                    .await
-                    .map_err(RunnerError::TpsEvaluatorError)?,
-            );
+            let mut tps_evaluations = tps_evaluator
+                .evaluate(&direct_evaluator_input)
+                .await
+                .map_err(RunnerError::TpsEvaluatorError)?;
+            progress
+                .evaluation_results
+                .lock()
+                .unwrap()
+                .extend(tps_evaluations.iter().cloned());
+            evaluation_results.append(&mut tps_evaluations);
             debug!("TPS evaluator done");
         }
 
+        *progress.phase.lock().unwrap() = RunnerPhase::MetricsFetchDelay;
         tokio::time::sleep_until(metrics_fetch_delay_time).await;
 
-        debug!("Collecting second round of baseline metrics");
-        let second_baseline_metrics =
-            Self::collect_metrics(&self.baseline_metric_collector).await?;
-
-        debug!("Collecting second round of target metrics");
-        let second_target_metrics = Self::collect_metrics(target_metric_collector).await?;
+        *progress.phase.lock().unwrap() = RunnerPhase::SecondScrape;
+        debug!("Collecting second round of metrics from both nodes");
+        let (second_baseline_metrics, second_target_metrics) = tokio::try_join!(
+            Self::collect_metrics(&self.baseline_metric_collector),
+            Self::collect_metrics(target_metric_collector),
+        )?;
 
         let second_baseline_metrics = self.parse_response(second_baseline_metrics)?;
         let second_target_metrics = self.parse_response(second_target_metrics)?;
@@ -191,23 +220,39 @@ impl<M: MetricCollector> Runner for BlockingRunner<M> {
             target_system_information,
         };
 
-        for evaluator in &self.evaluators {
-            let mut local_evaluation_results = match evaluator {
+        *progress.phase.lock().unwrap() = RunnerPhase::Evaluators;
+
+        // These evaluators are pure functions of the inputs we already
+        // collected above and are independent of each other, so we dispatch
+        // them all at once instead of awaiting them one at a time.
+        let evaluator_futures = self.evaluators.iter().map(|evaluator| async {
+            let local_evaluation_results = match evaluator {
                 EvaluatorType::Metrics(evaluator) => evaluator
                     .evaluate(&metrics_evaluator_input)
                     .await
-                    .map_err(RunnerError::MetricEvaluatorError)?,
+                    .map_err(RunnerError::MetricEvaluatorError),
                 EvaluatorType::SystemInformation(evaluator) => evaluator
                     .evaluate(&system_information_evaluator_input)
                     .await
-                    .map_err(RunnerError::SystemInformationEvaluatorError)?,
+                    .map_err(RunnerError::SystemInformationEvaluatorError),
                 // The TPS evaluator has already been used above.
-                EvaluatorType::Tps(_) => vec![],
+                EvaluatorType::Tps(_) => Ok(vec![]),
                 EvaluatorType::Latency(evaluator) => evaluator
                     .evaluate(&direct_evaluator_input)
                     .await
-                    .map_err(RunnerError::LatencyEvaluatorError)?,
-            };
+                    .map_err(RunnerError::LatencyEvaluatorError),
+            }?;
+            // Record each evaluator's results as soon as it finishes, so a
+            // deadline hit while others are still running keeps them.
+            progress
+                .evaluation_results
+                .lock()
+                .unwrap()
+                .extend(local_evaluation_results.iter().cloned());
+            Ok::<_, RunnerError>(local_evaluation_results)
+        });
+
+        for mut local_evaluation_results in try_join_all(evaluator_futures).await? {
             evaluation_results.append(&mut local_evaluation_results);
         }
 
@@ -216,3 +261,51 @@ impl<M: MetricCollector> Runner for BlockingRunner<M> {
         Ok(complete_evaluation)
     }
 }
+
+/// This runner doesn't block in the multithreading sense, but from the user
+/// perspective. To run the health check, we pull metrics once, wait, and then
+/// pull the metrics again. It does not support continually running beyond this
+/// point. You can imagine smarter versions of this where you store the last seen
+/// set of metrics, then compare against that, or perhaps even multiple previously
+/// seen sets of metrics and do more complex analysis. Additionally we could leverage
+/// things like long polling +/ sticky routing to make it that the client request
+/// doesn't just hang waiting for the run to complete.
+#[async_trait]
+impl<M: MetricCollector> Runner for BlockingRunner<M> {
+    async fn run<T: MetricCollector>(
+        &self,
+        target_node_address: &NodeAddress,
+        target_metric_collector: &T,
+    ) -> Result<EvaluationSummary, RunnerError> {
+        let progress = RunProgress::default();
+
+        let deadline_secs = match self.args.run_deadline_secs {
+            Some(deadline_secs) => deadline_secs,
+            None => {
+                return self
+                    .run_inner(target_node_address, target_metric_collector, &progress)
+                    .await
+            }
+        };
+
+        tokio::select! {
+            result = self.run_inner(target_node_address, target_metric_collector, &progress) => result,
+            _ = tokio::time::sleep(Duration::from_secs(deadline_secs)) => {
+                let phase = *progress.phase.lock().unwrap();
+                let partial_evaluation_results = progress.evaluation_results.lock().unwrap().clone();
+                warn!(
+                    "Run for {} hit its {}s deadline while {}, carrying {} partial evaluation result(s)",
+                    target_node_address.url,
+                    deadline_secs,
+                    phase,
+                    partial_evaluation_results.len(),
+                );
+                Err(RunnerError::Timeout {
+                    phase,
+                    deadline_secs,
+                    partial_evaluation_results,
+                })
+            },
+        }
+    }
+}
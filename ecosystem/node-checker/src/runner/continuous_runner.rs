@@ -0,0 +1,392 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{Runner, RunnerError};
+use crate::{
+    configuration::NodeAddress,
+    evaluator::{EvaluationSummary, Evaluator},
+    evaluators::{
+        direct::{DirectEvaluatorInput, NodeIdentityEvaluator},
+        metrics::{parse_metrics, MetricsEvaluatorInput},
+        system_information::SystemInformationEvaluatorInput,
+        EvaluatorType,
+    },
+    metric_collector::MetricCollector,
+    server::NodeInformation,
+};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use clap::Parser;
+use log::{debug, info, warn};
+use poem_openapi::Object as PoemObject;
+use prometheus_parse::Scrape as PrometheusScrape;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+use tokio::sync::RwLock;
+use tokio::time::Duration;
+
+#[derive(Clone, Debug, Deserialize, Parser, PoemObject, Serialize)]
+pub struct ContinuousRunnerArgs {
+    /// How often, in seconds, the background loop scrapes the baseline and
+    /// each tracked target node. Must be greater than 0.
+    #[clap(long, default_value = "5")]
+    pub scrape_interval_secs: u64,
+
+    /// How many scrapes to retain per node in the sliding window before the
+    /// oldest entry is evicted to make room for the newest one. Must be at
+    /// least 2, since the evaluators compare the two most recent scrapes.
+    #[clap(long, default_value = "12")]
+    pub window_size: usize,
+}
+
+/// A bounded, FIFO window of previously seen scrapes for a single node.
+/// Pushing past `window_size` evicts the oldest entry.
+#[derive(Debug, Default)]
+struct ScrapeWindow {
+    scrapes: VecDeque<PrometheusScrape>,
+}
+
+impl ScrapeWindow {
+    fn push(&mut self, scrape: PrometheusScrape, window_size: usize) {
+        self.scrapes.push_back(scrape);
+        while self.scrapes.len() > window_size {
+            self.scrapes.pop_front();
+        }
+    }
+
+    /// Returns the two most recent scrapes, oldest first, if we have them.
+    fn last_two(&self) -> Option<(&PrometheusScrape, &PrometheusScrape)> {
+        let len = self.scrapes.len();
+        if len < 2 {
+            return None;
+        }
+        Some((&self.scrapes[len - 2], &self.scrapes[len - 1]))
+    }
+}
+
+/// This runner maintains a sliding window of previously seen metrics scrapes
+/// for the baseline node and for every target node it has been asked to
+/// evaluate at least once. A background task continually scrapes each known
+/// node on `scrape_interval_secs`, so `run` can serve a health check by
+/// evaluating the two (or more, in the future) most recently stored rounds
+/// instead of blocking on fresh scrapes. Once a target's window has been
+/// primed, `run` does not wait on any metric scrape, at the cost of the
+/// evaluation being based on data that is up to one scrape interval stale;
+/// it still collects system information from both nodes and runs the
+/// direct evaluators (e.g. TPS, latency) on every call.
+/// Unlike `BlockingRunner`, which can only ever compare the two points it
+/// just collected, the retained window also opens the door to evaluators
+/// that look at trends (e.g. monotonicity) across the whole window rather
+/// than a single pair of samples.
+#[derive(Debug)]
+pub struct ContinuousRunner<M: MetricCollector> {
+    args: ContinuousRunnerArgs,
+    baseline_node_information: NodeInformation,
+    baseline_metric_collector: M,
+    node_identity_evaluator: NodeIdentityEvaluator,
+    evaluators: Vec<EvaluatorType>,
+    baseline_window: Arc<RwLock<ScrapeWindow>>,
+    target_windows: Arc<RwLock<HashMap<NodeAddress, Arc<RwLock<ScrapeWindow>>>>>,
+}
+
+impl<M: MetricCollector> ContinuousRunner<M> {
+    pub fn new(
+        args: ContinuousRunnerArgs,
+        baseline_node_information: NodeInformation,
+        baseline_metric_collector: M,
+        node_identity_evaluator: NodeIdentityEvaluator,
+        evaluators: Vec<EvaluatorType>,
+    ) -> Result<Self> {
+        // `prime_window` below loops until a window holds two scrapes, and
+        // the background scrape loops use `scrape_interval_secs` as a tick
+        // period, so these args being in range isn't optional: a `window_size`
+        // under 2 would make priming spin forever, and a `scrape_interval_secs`
+        // of 0 would make `tokio::time::interval` panic. `ContinuousRunnerArgs`
+        // is also loaded straight from config via `Deserialize`, not just
+        // parsed by clap, so we validate here rather than relying on a clap
+        // value parser to catch it.
+        anyhow::ensure!(
+            args.window_size >= 2,
+            "window_size must be at least 2 (the evaluators compare the two most recent scrapes), got {}",
+            args.window_size,
+        );
+        anyhow::ensure!(
+            args.scrape_interval_secs > 0,
+            "scrape_interval_secs must be greater than 0",
+        );
+
+        let baseline_window = Arc::new(RwLock::new(ScrapeWindow::default()));
+
+        let runner = Self {
+            args,
+            baseline_node_information,
+            baseline_metric_collector,
+            node_identity_evaluator,
+            evaluators,
+            baseline_window,
+            target_windows: Arc::new(RwLock::new(HashMap::new())),
+        };
+
+        runner.spawn_baseline_scrape_loop();
+
+        Ok(runner)
+    }
+
+    /// Spawn a task that scrapes the baseline node on `scrape_interval_secs`
+    /// forever, pushing each successfully parsed scrape into the baseline
+    /// window.
+    fn spawn_baseline_scrape_loop(&self) {
+        let metric_collector = self.baseline_metric_collector.clone();
+        let window = self.baseline_window.clone();
+        let interval = Duration::from_secs(self.args.scrape_interval_secs);
+        let window_size = self.args.window_size;
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                match Self::scrape(&metric_collector).await {
+                    Ok(scrape) => window.write().await.push(scrape, window_size),
+                    Err(error) => {
+                        warn!(
+                            "Continuous runner failed to scrape baseline node: {:#}",
+                            error
+                        )
+                    }
+                }
+            }
+        });
+    }
+
+    /// Spawn a task that scrapes a single target node on `scrape_interval_secs`
+    /// forever, pushing each successfully parsed scrape into that target's
+    /// window. This is started the first time `run` is called for a given
+    /// `NodeAddress`.
+    fn spawn_target_scrape_loop<T: MetricCollector>(
+        &self,
+        target_metric_collector: T,
+        window: Arc<RwLock<ScrapeWindow>>,
+    ) {
+        let interval = Duration::from_secs(self.args.scrape_interval_secs);
+        let window_size = self.args.window_size;
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                match Self::scrape(&target_metric_collector).await {
+                    Ok(scrape) => window.write().await.push(scrape, window_size),
+                    Err(error) => {
+                        warn!(
+                            "Continuous runner failed to scrape target node: {:#}",
+                            error
+                        )
+                    }
+                }
+            }
+        });
+    }
+
+    async fn scrape<MC: MetricCollector>(metric_collector: &MC) -> Result<PrometheusScrape> {
+        let lines = metric_collector
+            .collect_metrics()
+            .await
+            .context("Failed to collect metrics")?;
+        parse_metrics(lines).context("Failed to parse metrics response")
+    }
+
+    /// Make sure `window` holds at least two scrapes, fetching fresh ones
+    /// directly (rather than waiting on the background scrape loop) until
+    /// it does. This is what lets a brand new target's first `run` succeed
+    /// immediately instead of racing the background loop's next tick: that
+    /// race meant whether `run` got one stored scrape or two depended on
+    /// nondeterministic scheduling, which surfaced as a spurious error even
+    /// though the runner could trivially have collected the second point
+    /// itself. Relies on `ContinuousRunner::new` having rejected a
+    /// `window_size` under 2, since `ScrapeWindow::push` would otherwise cap
+    /// the window below the length this loop is waiting for and spin forever.
+    async fn prime_window<MC: MetricCollector>(
+        window: &RwLock<ScrapeWindow>,
+        metric_collector: &MC,
+        window_size: usize,
+    ) -> Result<(), RunnerError> {
+        while window.read().await.scrapes.len() < 2 {
+            let scrape = Self::scrape(metric_collector)
+                .await
+                .map_err(RunnerError::MetricCollectorError)?;
+            window.write().await.push(scrape, window_size);
+        }
+        Ok(())
+    }
+
+    /// Make sure we have a window (and a background scrape loop) for this
+    /// target, creating and starting one if this is the first time we've
+    /// seen it.
+    async fn window_for_target<T: MetricCollector>(
+        &self,
+        target_node_address: &NodeAddress,
+        target_metric_collector: &T,
+    ) -> Arc<RwLock<ScrapeWindow>> {
+        if let Some(window) = self.target_windows.read().await.get(target_node_address) {
+            return window.clone();
+        }
+
+        let mut target_windows = self.target_windows.write().await;
+        // Check again now that we hold the write lock, in case another call
+        // raced us to create the window for this target.
+        if let Some(window) = target_windows.get(target_node_address) {
+            return window.clone();
+        }
+
+        info!(
+            "Continuous runner has not seen target {} before, starting to track it",
+            target_node_address.url
+        );
+        let window = Arc::new(RwLock::new(ScrapeWindow::default()));
+        target_windows.insert(target_node_address.clone(), window.clone());
+        self.spawn_target_scrape_loop(target_metric_collector.clone(), window.clone());
+
+        window
+    }
+}
+
+#[async_trait]
+impl<M: MetricCollector> Runner for ContinuousRunner<M> {
+    async fn run<T: MetricCollector>(
+        &self,
+        target_node_address: &NodeAddress,
+        target_metric_collector: &T,
+    ) -> Result<EvaluationSummary, RunnerError> {
+        info!(
+            "Running evaluation for {} against stored scrape window",
+            target_node_address.url
+        );
+
+        let direct_evaluator_input = DirectEvaluatorInput {
+            baseline_node_information: self.baseline_node_information.clone(),
+            target_node_address: target_node_address.clone(),
+        };
+
+        debug!("Confirming node identity matches");
+        let node_identity_evaluations = self
+            .node_identity_evaluator
+            .evaluate(&direct_evaluator_input)
+            .await
+            .map_err(RunnerError::NodeIdentityEvaluatorError)?;
+
+        // Exit early if a node identity evaluation returned a non-passing result.
+        for evaluation in &node_identity_evaluations {
+            if evaluation.score != 100 {
+                return Ok(EvaluationSummary::from(node_identity_evaluations));
+            }
+        }
+
+        debug!("Collecting system information from baseline node");
+        let baseline_system_information = self
+            .baseline_metric_collector
+            .collect_system_information()
+            .await
+            .map_err(RunnerError::MetricCollectorError)?;
+
+        debug!("Collecting system information from target node");
+        let target_system_information = target_metric_collector
+            .collect_system_information()
+            .await
+            .map_err(RunnerError::MetricCollectorError)?;
+
+        // Make sure both windows have a background scrape loop running, then
+        // prime each with two scrapes (the minimum `last_two` needs) if it
+        // doesn't have them yet, so a brand new target doesn't have to wait
+        // on the background loop, and doesn't fail, before its first
+        // evaluation.
+        let target_window = self
+            .window_for_target(target_node_address, target_metric_collector)
+            .await;
+
+        Self::prime_window(
+            &self.baseline_window,
+            &self.baseline_metric_collector,
+            self.args.window_size,
+        )
+        .await?;
+
+        Self::prime_window(
+            &target_window,
+            target_metric_collector,
+            self.args.window_size,
+        )
+        .await?;
+
+        let mut evaluation_results = node_identity_evaluations;
+
+        let tps_evaluator = self.evaluators.iter().find_map(|e| match e {
+            EvaluatorType::Tps(evaluator) => Some(evaluator),
+            _ => None,
+        });
+
+        if let Some(tps_evaluator) = tps_evaluator {
+            debug!("Running TPS evaluator");
+            evaluation_results.append(
+                &mut tps_evaluator
+                    .evaluate(&direct_evaluator_input)
+                    .await
+                    .map_err(RunnerError::TpsEvaluatorError)?,
+            );
+        }
+
+        let baseline_window = self.baseline_window.read().await;
+        let target_window = target_window.read().await;
+
+        // `prime_window` plus the `window_size >= 2` invariant enforced in
+        // `new` mean `last_two` should always succeed here; this is a
+        // defensive fallback, reported distinctly from an actual collector
+        // failure in case that invariant is ever violated.
+        let (previous_baseline_metrics, latest_baseline_metrics) = baseline_window
+            .last_two()
+            .ok_or_else(|| RunnerError::WindowNotPrimed {
+                node_description: "baseline node".to_string(),
+            })?;
+        let (previous_target_metrics, latest_target_metrics) = target_window
+            .last_two()
+            .ok_or_else(|| RunnerError::WindowNotPrimed {
+                node_description: format!("target node {}", target_node_address.url),
+            })?;
+
+        let metrics_evaluator_input = MetricsEvaluatorInput {
+            previous_baseline_metrics: previous_baseline_metrics.clone(),
+            previous_target_metrics: previous_target_metrics.clone(),
+            latest_baseline_metrics: latest_baseline_metrics.clone(),
+            latest_target_metrics: latest_target_metrics.clone(),
+        };
+
+        let system_information_evaluator_input = SystemInformationEvaluatorInput {
+            baseline_system_information,
+            target_system_information,
+        };
+
+        for evaluator in &self.evaluators {
+            let mut local_evaluation_results = match evaluator {
+                EvaluatorType::Metrics(evaluator) => evaluator
+                    .evaluate(&metrics_evaluator_input)
+                    .await
+                    .map_err(RunnerError::MetricEvaluatorError)?,
+                EvaluatorType::SystemInformation(evaluator) => evaluator
+                    .evaluate(&system_information_evaluator_input)
+                    .await
+                    .map_err(RunnerError::SystemInformationEvaluatorError)?,
+                // The TPS evaluator has already been used above.
+                EvaluatorType::Tps(_) => vec![],
+                EvaluatorType::Latency(evaluator) => evaluator
+                    .evaluate(&direct_evaluator_input)
+                    .await
+                    .map_err(RunnerError::LatencyEvaluatorError)?,
+            };
+            evaluation_results.append(&mut local_evaluation_results);
+        }
+
+        Ok(EvaluationSummary::from(evaluation_results))
+    }
+}
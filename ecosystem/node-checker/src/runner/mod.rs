@@ -0,0 +1,101 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+mod blocking_runner;
+mod continuous_runner;
+
+pub use blocking_runner::{BlockingRunner, BlockingRunnerArgs};
+pub use continuous_runner::{ContinuousRunner, ContinuousRunnerArgs};
+
+use crate::{
+    configuration::NodeAddress,
+    evaluator::{EvaluationResult, EvaluationSummary},
+    metric_collector::MetricCollector,
+};
+use async_trait::async_trait;
+use std::fmt;
+use thiserror::Error;
+
+/// A Runner is the entity responsible for actually driving a health check to
+/// completion: collecting metrics (and other information) from the baseline
+/// and target nodes and feeding them to the configured evaluators to produce
+/// an EvaluationSummary. Different implementations can make different
+/// tradeoffs about when that information is collected, e.g. blocking to
+/// collect it fresh for every call vs serving from previously collected data.
+#[async_trait]
+pub trait Runner: Sync + Send + 'static {
+    async fn run<T: MetricCollector>(
+        &self,
+        target_node_address: &NodeAddress,
+        target_metric_collector: &T,
+    ) -> Result<EvaluationSummary, RunnerError>;
+}
+
+/// A coarse-grained phase of `Runner::run`. Used to report, on a timeout,
+/// what the runner was doing when the deadline elapsed.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum RunnerPhase {
+    #[default]
+    NodeIdentityCheck,
+    FirstScrape,
+    Tps,
+    MetricsFetchDelay,
+    SecondScrape,
+    Evaluators,
+}
+
+impl fmt::Display for RunnerPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            RunnerPhase::NodeIdentityCheck => "confirming node identity",
+            RunnerPhase::FirstScrape => "collecting the first round of metrics",
+            RunnerPhase::Tps => "running the TPS evaluator",
+            RunnerPhase::MetricsFetchDelay => "waiting out the metrics fetch delay",
+            RunnerPhase::SecondScrape => "collecting the second round of metrics",
+            RunnerPhase::Evaluators => "running the metrics/system-information/latency evaluators",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RunnerError {
+    #[error("Failed to parse metrics response: {0:#}")]
+    ParseMetricsError(anyhow::Error),
+
+    #[error("Error from the metric collector: {0:#}")]
+    MetricCollectorError(anyhow::Error),
+
+    #[error("Error from the node identity evaluator: {0:#}")]
+    NodeIdentityEvaluatorError(anyhow::Error),
+
+    #[error("Error from the metrics evaluator: {0:#}")]
+    MetricEvaluatorError(anyhow::Error),
+
+    #[error("Error from the system information evaluator: {0:#}")]
+    SystemInformationEvaluatorError(anyhow::Error),
+
+    #[error("Error from the TPS evaluator: {0:#}")]
+    TpsEvaluatorError(anyhow::Error),
+
+    #[error("Error from the latency evaluator: {0:#}")]
+    LatencyEvaluatorError(anyhow::Error),
+
+    #[error("Scrape window for {node_description} is not primed yet (fewer than two rounds of metrics stored)")]
+    WindowNotPrimed { node_description: String },
+
+    /// Returned when a `Runner::run` with an overall deadline (e.g.
+    /// `BlockingRunnerArgs::run_deadline_secs`) hits that deadline before
+    /// completing. Keeping this a distinct, observable error (rather than
+    /// silently returning `Ok` with whatever we had) lets a caller tell a
+    /// timeout apart from a clean completion that happened to produce fewer
+    /// results. A caller that would rather degrade gracefully can still
+    /// build an `EvaluationSummary` out of `partial_evaluation_results`
+    /// itself.
+    #[error("Runner timed out after {deadline_secs}s while {phase}")]
+    Timeout {
+        phase: RunnerPhase,
+        deadline_secs: u64,
+        partial_evaluation_results: Vec<EvaluationResult>,
+    },
+}